@@ -0,0 +1,157 @@
+//! The parsed representation of an SBText project.
+//!
+//! `parser::parse_project` produces a `Project`; `semantic::analyze` checks
+//! it; `sb3_backend` lowers it to a `.sb3` (`python_backend` compiles the
+//! merged source text instead). Every type here
+//! derives `Serialize` so `--emit json` can dump a stable, documented JSON
+//! encoding of the parse result for external tooling (editors, test
+//! harnesses) without reimplementing the parser. The JSON shape mirrors the
+//! field layout below (struct fields become object keys, `Vec`s become
+//! arrays), except that the raw asset bytes `Costume.data`/`Sound.data` are
+//! omitted and each `InputValue` carries its variant name in a `kind` key;
+//! `Block.next`/`Input`/`Field` retain the tree shape produced by
+//! the parser rather than the flattened id-map shape `sb3_backend` lowers
+//! them to.
+
+use crate::diagnostics::Span;
+use serde::Serialize;
+
+/// A full SBText project: the Stage plus every sprite, in source order.
+#[derive(Clone, Debug, Serialize)]
+pub struct Project {
+    pub targets: Vec<Target>,
+}
+
+impl Project {
+    /// All targets, Stage first. This is the order `sb3_backend` writes
+    /// `project.json`'s `targets` array in.
+    pub fn all_targets(&self) -> impl Iterator<Item = &Target> {
+        self.targets.iter()
+    }
+}
+
+/// One target: the Stage, or a sprite.
+#[derive(Clone, Debug, Serialize)]
+pub struct Target {
+    pub name: String,
+    pub is_stage: bool,
+    pub span: Span,
+    pub variables: Vec<Variable>,
+    pub lists: Vec<ScratchList>,
+    pub broadcasts: Vec<Broadcast>,
+    pub costumes: Vec<Costume>,
+    pub sounds: Vec<Sound>,
+    pub scripts: Vec<Script>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Variable {
+    pub id: String,
+    pub name: String,
+    pub default: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScratchList {
+    pub id: String,
+    pub name: String,
+    pub default: Vec<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Broadcast {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Costume {
+    pub name: String,
+    pub asset_id: String,
+    pub data_format: String,
+    pub md5ext: String,
+    pub rotation_center_x: f64,
+    pub rotation_center_y: f64,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Sound {
+    pub name: String,
+    pub asset_id: String,
+    pub data_format: String,
+    pub md5ext: String,
+    pub rate: u32,
+    pub sample_count: u32,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+/// One top-level hat block and everything chained after it via `next`.
+#[derive(Clone, Debug, Serialize)]
+pub struct Script {
+    pub root: Block,
+}
+
+/// A single block in a script. C-blocks (`if`, `repeat`, `forever`, ...)
+/// attach their body as a `Substack` input rather than as a separate field,
+/// matching how Scratch's own block model nests statements.
+#[derive(Clone, Debug, Serialize)]
+pub struct Block {
+    pub id: String,
+    pub opcode: String,
+    pub span: Span,
+    pub inputs: Vec<Input>,
+    pub fields: Vec<Field>,
+    /// Present on `procedures_definition`/`procedures_call` blocks: the
+    /// `PROCCODE` that ties a call to its definition (see `proccode`).
+    pub mutation: Option<Mutation>,
+    pub shadow: bool,
+    pub x: f64,
+    pub y: f64,
+    pub next: Option<Box<Block>>,
+}
+
+/// The custom-block identity shared by a `procedures_definition` and every
+/// `procedures_call` that targets it. `argument_ids` lists the input names a
+/// call must fill, in declared order.
+#[derive(Clone, Debug, Serialize)]
+pub struct Mutation {
+    pub proccode: String,
+    pub argument_ids: Vec<String>,
+}
+
+/// The `PROCCODE` for a custom block named `name` taking `arg_count`
+/// arguments: the name followed by one `%s` placeholder per argument,
+/// matching how Scratch itself encodes a custom block's signature.
+pub fn proccode(name: &str, arg_count: usize) -> String {
+    let mut code = name.to_string();
+    for _ in 0..arg_count {
+        code.push_str(" %s");
+    }
+    code
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Input {
+    pub name: String,
+    pub value: InputValue,
+}
+
+/// What fills an input slot: a literal shadow, a reporter block that
+/// obscures the shadow, or (for C-blocks) the substack of the block's body.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum InputValue {
+    Shadow { type_code: i32, value: String },
+    Reporter(Box<Block>),
+    Substack(Box<Block>),
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+    pub id: Option<String>,
+}
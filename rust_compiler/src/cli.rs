@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -6,19 +6,89 @@ use std::path::PathBuf;
     name = "sbtext-rs",
     about = "Rust entrypoint for SBText compilation (import resolution in Rust, Python backend optional)."
 )]
-pub struct Args {
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compile a project and emit an artifact (tokens, AST, JSON, merged source, or `.sb3`).
+    Build(BuildArgs),
+    /// Resolve imports and run semantic analysis, reporting diagnostics without building anything.
+    Check(CheckArgs),
+    /// Scaffold a new SBText project.
+    New(NewArgs),
+    /// Print the symbols provided by the bundled standard library.
+    ListStd,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct BuildArgs {
     #[arg(value_name = "INPUT")]
     pub input: PathBuf,
 
-    #[arg(value_name = "OUTPUT")]
+    #[arg(value_name = "OUTPUT", help = "Where to write the emitted artifact. Defaults to stdout for all targets except `sb3`.")]
     pub output: Option<PathBuf>,
 
+    #[arg(long, value_enum, default_value = "sb3", help = "What to emit.")]
+    pub emit: EmitTarget,
+
     #[arg(long, help = "Disable automatic SVG normalization to 64x64 (forwarded to Python backend).")]
     pub no_svg_scale: bool,
 
-    #[arg(long, help = "Write merged source after resolving imports to this path.")]
-    pub emit_merged: Option<PathBuf>,
-
     #[arg(long, help = "Use native Rust backend for .sb3 output instead of Python backend.")]
     pub no_python_backend: bool,
+
+    #[arg(long, help = "Don't merge in the bundled standard library.")]
+    pub no_std: bool,
+
+    #[arg(long, value_enum, default_value = "human", help = "How to render diagnostics.")]
+    pub error_format: ErrorFormat,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct CheckArgs {
+    #[arg(value_name = "INPUT")]
+    pub input: PathBuf,
+
+    #[arg(long, help = "Don't merge in the bundled standard library.")]
+    pub no_std: bool,
+
+    #[arg(long, value_enum, default_value = "human", help = "How to render diagnostics.")]
+    pub error_format: ErrorFormat,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct NewArgs {
+    #[arg(value_name = "NAME")]
+    pub name: String,
+
+    #[arg(long, default_value = ".", help = "Directory to create the project in.")]
+    pub path: PathBuf,
+}
+
+/// The artifact `build` produces, selected via `--emit`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum EmitTarget {
+    /// The raw lexer token stream, one token per line.
+    Tokens,
+    /// The pretty-printed `Project` AST (`{:#?}`).
+    Ast,
+    /// A serde JSON serialization of the parsed `Project`.
+    Json,
+    /// The resolved source after import merging.
+    Merged,
+    /// The compiled `.sb3` archive (the default).
+    Sb3,
+}
+
+/// How `build`/`check` render diagnostics when something fails to parse or
+/// validate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// A rustc-style caret-underlined snippet (the default).
+    Human,
+    /// One JSON object per diagnostic, for editors and language servers.
+    Json,
 }
@@ -0,0 +1,208 @@
+//! Structured diagnostics with source spans.
+//!
+//! `Lexer`, `Parser`, and `semantic::analyze` are meant to carry byte-offset
+//! `Span`s through to their failures so `--error-format` can render either a
+//! rustc-style caret-underlined snippet (`human`, the default) or a stream of
+//! machine-readable objects (`json`) for editors and language servers.
+//! Because imports are merged before validation, `SourceMap` also maps an
+//! offset in the merged buffer back to the file it actually came from.
+
+use serde::Serialize;
+
+/// A byte-offset range into a source buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+/// Only `Error` is constructed today: `Lexer`/`Parser`/`semantic::analyze`
+/// all fail fast on the first problem rather than collecting diagnostics of
+/// mixed severity. Extend this once something produces non-fatal findings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Sentinel returned by a `run_*` command once its error has already been
+/// printed via `SourceMap::report`, so `main` doesn't print it a second time.
+/// Mirrors rustc's `ErrorGuaranteed`.
+#[derive(Debug)]
+pub struct Reported;
+
+impl std::fmt::Display for Reported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "diagnostics already reported")
+    }
+}
+
+impl std::error::Error for Reported {}
+
+/// Resolves byte offsets in a merged source buffer to 1-based line/column,
+/// and back to the imported file an offset actually originated from.
+pub struct SourceMap {
+    default_file: String,
+    text: String,
+    line_starts: Vec<usize>,
+    /// `(merged_offset, origin_file)` boundaries recorded while
+    /// `imports::resolve_merged_source` concatenated each imported file,
+    /// sorted by `merged_offset`.
+    origins: Vec<(usize, String)>,
+}
+
+impl SourceMap {
+    pub fn new(default_file: impl Into<String>, text: impl Into<String>, origins: Vec<(usize, String)>) -> Self {
+        let text = text.into();
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        Self {
+            default_file: default_file.into(),
+            text,
+            line_starts,
+            origins,
+        }
+    }
+
+    fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        (line + 1, offset - self.line_starts[line] + 1)
+    }
+
+    fn origin_file(&self, offset: usize) -> &str {
+        self.origins
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= offset)
+            .map(|(_, file)| file.as_str())
+            .unwrap_or(&self.default_file)
+    }
+
+    fn line_text(&self, line: usize) -> &str {
+        let start = self.line_starts[line - 1];
+        let end = self.line_starts.get(line).copied().unwrap_or(self.text.len());
+        self.text[start..end].trim_end_matches('\n')
+    }
+
+    /// Prints `diagnostic` in the requested format and returns the sentinel
+    /// `Reported` error so callers can propagate failure with `?`.
+    pub fn report(&self, diagnostic: &Diagnostic, format: super::cli::ErrorFormat) -> Reported {
+        match format {
+            super::cli::ErrorFormat::Human => eprint!("{}", self.render_human(diagnostic)),
+            super::cli::ErrorFormat::Json => println!("{}", self.render_json(diagnostic)),
+        }
+        Reported
+    }
+
+    fn render_human(&self, diagnostic: &Diagnostic) -> String {
+        let (line, col) = self.line_col(diagnostic.span.start);
+        let file = self.origin_file(diagnostic.span.start);
+        let snippet = self.line_text(line);
+        let underline_len = (diagnostic.span.end - diagnostic.span.start).max(1);
+        let severity = match diagnostic.severity {
+            Severity::Error => "error",
+        };
+
+        let mut out = String::new();
+        out.push_str(&format!("{severity}[{}]: {}\n", diagnostic.code, diagnostic.message));
+        out.push_str(&format!("  --> {file}:{line}:{col}\n"));
+        out.push_str("   |\n");
+        out.push_str(&format!("{line:>3} | {snippet}\n"));
+        out.push_str(&format!("    | {}{}\n", " ".repeat(col - 1), "^".repeat(underline_len)));
+        out
+    }
+
+    fn render_json(&self, diagnostic: &Diagnostic) -> String {
+        let (line, col) = self.line_col(diagnostic.span.start);
+
+        #[derive(Serialize)]
+        struct JsonDiagnostic<'a> {
+            file: &'a str,
+            span: JsonSpan,
+            severity: Severity,
+            code: &'a str,
+            message: &'a str,
+        }
+        #[derive(Serialize)]
+        struct JsonSpan {
+            start: usize,
+            end: usize,
+            line: usize,
+            col: usize,
+        }
+
+        let json = JsonDiagnostic {
+            file: self.origin_file(diagnostic.span.start),
+            span: JsonSpan {
+                start: diagnostic.span.start,
+                end: diagnostic.span.end,
+                line,
+                col,
+            },
+            severity: diagnostic.severity,
+            code: diagnostic.code,
+            message: &diagnostic.message,
+        };
+        serde_json::to_string(&json).expect("diagnostic always serializes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_line_and_column_for_an_offset() {
+        let source_map = SourceMap::new("main.sbtext", "sprite A {\n    say \"hi\"\n}\n", vec![(0, "main.sbtext".to_string())]);
+        let say_offset = "sprite A {\n    ".len();
+        assert_eq!(source_map.line_col(say_offset), (2, 5));
+    }
+
+    #[test]
+    fn maps_an_offset_back_to_the_imported_file_it_came_from() {
+        let merged = "define helper() { return 1 }\nsprite A {\n    say \"hi\"\n}\n";
+        let stdlib_len = "define helper() { return 1 }\n".len();
+        let origins = vec![(0, "<stdlib>".to_string()), (stdlib_len, "main.sbtext".to_string())];
+        let source_map = SourceMap::new("main.sbtext", merged, origins);
+
+        assert_eq!(source_map.origin_file(0), "<stdlib>");
+        assert_eq!(source_map.origin_file(stdlib_len), "main.sbtext");
+        assert_eq!(source_map.origin_file(merged.len() - 1), "main.sbtext");
+    }
+}
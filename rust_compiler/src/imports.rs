@@ -0,0 +1,147 @@
+//! Import resolution: merges a project's `import "path"` statements (and
+//! the stdlib prelude, unless disabled) into one source buffer before
+//! lexing, the same way a C preprocessor splices `#include`s ahead of
+//! compilation.
+//!
+//! Each file is merged at most once, so diamond and cyclic imports are
+//! inert rather than duplicating or looping. The stdlib prelude, if
+//! requested, is always merged first so its `define`s are in scope for the
+//! rest of the project. Diagnostics point at offsets in the merged buffer,
+//! so `MergedSource` also tracks which imported file each stretch of the
+//! buffer came from, for `diagnostics::SourceMap` to resolve back.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// The origin recorded for stdlib text merged ahead of the user's project.
+pub const STDLIB_ORIGIN: &str = "<stdlib>";
+
+/// The merged source buffer plus a record of which file each stretch of it
+/// came from, so a byte offset in `text` can be mapped back to the
+/// imported file it originated in.
+pub struct MergedSource {
+    pub text: String,
+    /// `(merged_offset, origin_file)` boundaries, sorted by `merged_offset`.
+    pub origins: Vec<(usize, String)>,
+}
+
+/// Resolves `input`'s imports (and `stdlib`, if given) into one buffer.
+pub fn resolve_merged_source(input: &Path, stdlib: Option<&str>) -> Result<MergedSource> {
+    let mut text = String::new();
+    let mut origins = Vec::new();
+    let mut merged = HashSet::new();
+
+    if let Some(stdlib) = stdlib {
+        origins.push((text.len(), STDLIB_ORIGIN.to_string()));
+        append_lines(&mut text, stdlib);
+    }
+
+    merge_file(input, &mut text, &mut origins, &mut merged)?;
+    Ok(MergedSource { text, origins })
+}
+
+fn merge_file(
+    path: &Path,
+    out: &mut String,
+    origins: &mut Vec<(usize, String)>,
+    merged: &mut HashSet<PathBuf>,
+) -> Result<()> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("resolving import '{}'", path.display()))?;
+    if !merged.insert(canonical.clone()) {
+        return Ok(());
+    }
+
+    let source = std::fs::read_to_string(&canonical)
+        .with_context(|| format!("reading imported file '{}'", path.display()))?;
+    let dir = canonical.parent().map(Path::to_path_buf).unwrap_or_default();
+    let origin_name = canonical.display().to_string();
+
+    origins.push((out.len(), origin_name.clone()));
+    for line in source.lines() {
+        match line.trim_start().strip_prefix("import ") {
+            Some(rest) => {
+                let imported = parse_import_path(rest)?;
+                merge_file(&dir.join(imported), out, origins, merged)?;
+                // The nested import's text is now behind us; anything from
+                // here back belongs to this file again.
+                origins.push((out.len(), origin_name.clone()));
+            }
+            None => append_lines(out, line),
+        }
+    }
+    Ok(())
+}
+
+fn parse_import_path(rest: &str) -> Result<&str> {
+    let rest = rest.trim().trim_end_matches(';').trim();
+    let Some(path) = rest.strip_prefix('"').and_then(|s| s.strip_suffix('"')) else {
+        bail!("malformed import statement: expected import \"path\", got 'import {rest}'");
+    };
+    if path.is_empty() {
+        bail!("malformed import statement: expected import \"path\", got 'import {rest}'");
+    }
+    Ok(path)
+}
+
+fn append_lines(out: &mut String, lines: &str) {
+    out.push_str(lines);
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_temp(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        path
+    }
+
+    #[test]
+    fn merges_stdlib_ahead_of_user_imports() {
+        let dir = std::env::temp_dir().join(format!("sbtext-imports-test-stdlib-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main = write_temp(&dir, "main.sbtext", "sprite S { when_flag_clicked { say \"hi\" } }");
+
+        let merged = resolve_merged_source(&main, Some("define clamp(x) { return x }")).unwrap();
+
+        let clamp_at = merged.text.find("define clamp").unwrap();
+        let sprite_at = merged.text.find("sprite S").unwrap();
+        assert!(clamp_at < sprite_at, "stdlib must be merged ahead of the user's project");
+        assert_eq!(merged.origins[0], (0, STDLIB_ORIGIN.to_string()));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn resolves_a_nested_import_exactly_once() {
+        let dir = std::env::temp_dir().join(format!("sbtext-imports-test-nested-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_temp(&dir, "shared.sbtext", "define helper() { return 1 }");
+        let main = write_temp(
+            &dir,
+            "main.sbtext",
+            "import \"shared.sbtext\"\nimport \"shared.sbtext\"\nsprite S { when_flag_clicked { say \"hi\" } }",
+        );
+
+        let merged = resolve_merged_source(&main, None).unwrap();
+        assert_eq!(merged.text.matches("define helper").count(), 1);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// Regression test: `import "path` (missing its closing quote) used to
+    /// be silently accepted as path `path`, since `trim_matches('"')` only
+    /// requires a quote on *some* side, not both.
+    #[test]
+    fn rejects_an_import_missing_its_closing_quote() {
+        let err = parse_import_path("\"shared.sbtext").unwrap_err();
+        assert!(err.to_string().contains("malformed import statement"));
+    }
+}
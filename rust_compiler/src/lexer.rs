@@ -0,0 +1,224 @@
+//! Tokenizer for SBText source.
+//!
+//! Produces a flat `Vec<Token>`, each carrying the byte-offset `Span` it
+//! came from, so `Parser` and `semantic::analyze` can report diagnostics
+//! that point at real source locations.
+
+use crate::diagnostics::{Diagnostic, Span};
+use anyhow::Result;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Ident,
+    Number,
+    Str,
+    Punct,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+pub struct Lexer<'s> {
+    source: &'s str,
+    bytes: &'s [u8],
+    pos: usize,
+}
+
+impl<'s> Lexer<'s> {
+    pub fn new(source: &'s str) -> Self {
+        Self {
+            source,
+            bytes: source.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    pub fn tokenize(&mut self) -> Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_trivia();
+            let Some(c) = self.peek() else { break };
+            let start = self.pos;
+
+            if c.is_ascii_alphabetic() || c == '_' {
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_') {
+                    self.pos += 1;
+                }
+                tokens.push(self.token(TokenKind::Ident, start));
+            } else if c.is_ascii_digit() {
+                while matches!(self.peek(), Some(c) if c.is_ascii_digit() || c == '.') {
+                    self.pos += 1;
+                }
+                tokens.push(self.token(TokenKind::Number, start));
+            } else if c == '"' {
+                self.pos += 1;
+                let mut text = String::new();
+                loop {
+                    match self.peek() {
+                        None => {
+                            return Err(Diagnostic::error(
+                                "E001",
+                                "unterminated string literal",
+                                Span::new(start, self.pos),
+                            )
+                            .into())
+                        }
+                        Some('"') => {
+                            self.pos += 1;
+                            break;
+                        }
+                        Some('\\') => {
+                            self.pos += 1;
+                            let Some(escaped) = self.peek() else {
+                                return Err(Diagnostic::error(
+                                    "E001",
+                                    "unterminated string literal",
+                                    Span::new(start, self.pos),
+                                )
+                                .into());
+                            };
+                            let unescaped = match escaped {
+                                'n' => '\n',
+                                't' => '\t',
+                                'r' => '\r',
+                                '0' => '\0',
+                                '\\' => '\\',
+                                '"' => '"',
+                                other => {
+                                    return Err(Diagnostic::error(
+                                        "E003",
+                                        format!("unknown escape sequence '\\{other}'"),
+                                        Span::new(self.pos - 1, self.pos + other.len_utf8()),
+                                    )
+                                    .into())
+                                }
+                            };
+                            text.push(unescaped);
+                            self.pos += escaped.len_utf8();
+                        }
+                        Some(ch) => {
+                            text.push(ch);
+                            self.pos += ch.len_utf8();
+                        }
+                    }
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Str,
+                    text,
+                    span: Span::new(start, self.pos),
+                });
+            } else if c == '=' && self.bytes.get(self.pos + 1) == Some(&b'=') {
+                self.pos += 2;
+                tokens.push(self.token(TokenKind::Punct, start));
+            } else if "{}()+-*/<>=,".contains(c) {
+                self.pos += c.len_utf8();
+                tokens.push(self.token(TokenKind::Punct, start));
+            } else {
+                return Err(Diagnostic::error(
+                    "E002",
+                    format!("unexpected character '{c}'"),
+                    Span::new(start, start + c.len_utf8()),
+                )
+                .into());
+            }
+        }
+        Ok(tokens)
+    }
+
+    fn token(&self, kind: TokenKind, start: usize) -> Token {
+        Token {
+            kind,
+            text: self.source[start..self.pos].to_string(),
+            span: Span::new(start, self.pos),
+        }
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => self.pos += c.len_utf8(),
+                Some('/') if self.bytes.get(self.pos + 1) == Some(&b'/') => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_a_say_statement() {
+        let mut lexer = Lexer::new(r#"say "hi""#);
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[0].text, "say");
+        assert_eq!(tokens[1].kind, TokenKind::Str);
+        assert_eq!(tokens[1].text, "hi");
+    }
+
+    #[test]
+    fn skips_line_comments() {
+        let mut lexer = Lexer::new("// a comment\nsay \"hi\"");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[0].text, "say");
+    }
+
+    /// Regression test: an escaped quote used to be kept in the token text
+    /// verbatim (backslash and all) instead of being unescaped.
+    #[test]
+    fn unescapes_an_escaped_quote_in_a_string_literal() {
+        let mut lexer = Lexer::new(r#"say "say \"hi\"""#);
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[1].text, r#"say "hi""#);
+    }
+
+    /// Regression test: a trailing backslash with nothing after it used to
+    /// advance `pos` past the end of the source and panic on the next peek.
+    #[test]
+    fn reports_unterminated_string_on_a_trailing_backslash() {
+        let mut lexer = Lexer::new(r#"say "a\"#);
+        let err = lexer.tokenize().unwrap_err();
+        let diagnostic = err.downcast_ref::<Diagnostic>().unwrap();
+        assert_eq!(diagnostic.code, "E001");
+    }
+
+    #[test]
+    fn reports_unterminated_string_with_a_span() {
+        let mut lexer = Lexer::new(r#"say "oops"#);
+        let err = lexer.tokenize().unwrap_err();
+        let diagnostic = err.downcast_ref::<Diagnostic>().unwrap();
+        assert_eq!(diagnostic.code, "E001");
+        assert_eq!(diagnostic.span.start, 4);
+    }
+
+    /// Regression test: `\n`/`\t` used to drop the backslash and keep the
+    /// next character verbatim, so `\n` lexed to a literal 'n' instead of a
+    /// newline.
+    #[test]
+    fn maps_known_escapes_to_their_real_characters() {
+        let mut lexer = Lexer::new(r#"say "a\nb\tc""#);
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[1].text, "a\nb\tc");
+    }
+
+    #[test]
+    fn reports_an_unknown_escape_sequence() {
+        let mut lexer = Lexer::new(r#"say "a\qb""#);
+        let err = lexer.tokenize().unwrap_err();
+        let diagnostic = err.downcast_ref::<Diagnostic>().unwrap();
+        assert_eq!(diagnostic.code, "E003");
+    }
+}
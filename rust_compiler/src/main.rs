@@ -1,55 +1,158 @@
-mod cli;
 mod ast;
+mod cli;
+mod diagnostics;
 mod imports;
 mod lexer;
 mod parser;
 mod python_backend;
+mod sb3_backend;
 mod semantic;
+mod stdlib;
+mod svg;
 
-use anyhow::Result;
 use clap::Parser;
-use cli::Args;
+use cli::{BuildArgs, CheckArgs, Cli, Command, EmitTarget, ErrorFormat, NewArgs};
+use diagnostics::{Diagnostic, Reported, SourceMap};
 use imports::resolve_merged_source;
 use lexer::Lexer;
 use parser::Parser as SbParser;
 use semantic::analyze as semantic_analyze;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+fn main() {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Build(args) => run_build(args),
+        Command::Check(args) => run_check(args),
+        Command::New(args) => run_new(args),
+        Command::ListStd => run_list_std(),
+    };
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+    if let Err(err) = result {
+        if err.downcast_ref::<Reported>().is_none() {
+            eprintln!("error: {err:#}");
+        }
+        std::process::exit(1);
+    }
+}
+
+fn run_build(args: BuildArgs) -> anyhow::Result<()> {
     let input = canonicalize_file(&args.input)?;
-    let merged = resolve_merged_source(&input)?;
-    validate_project(&merged)?;
+    let merged = resolve_merged_source(&input, std_prelude(args.no_std))?;
+    let source_map = SourceMap::new(args.input.display().to_string(), merged.text.clone(), merged.origins);
 
-    if let Some(emit_path) = args.emit_merged {
-        std::fs::write(&emit_path, merged.as_bytes())?;
+    match args.emit {
+        EmitTarget::Tokens => {
+            let tokens = try_tokenize(&merged.text, &source_map, args.error_format)?;
+            let rendered = tokens.iter().map(|t| format!("{t:?}")).collect::<Vec<_>>().join("\n");
+            emit_output(&args.output, &rendered)?;
+        }
+        EmitTarget::Merged => {
+            emit_output(&args.output, &merged.text)?;
+        }
+        EmitTarget::Ast => {
+            let project = try_parse_and_validate(&merged.text, &source_map, args.error_format)?;
+            emit_output(&args.output, &format!("{project:#?}"))?;
+        }
+        EmitTarget::Json => {
+            let project = try_parse_and_validate(&merged.text, &source_map, args.error_format)?;
+            emit_output(&args.output, &serde_json::to_string_pretty(&project)?)?;
+        }
+        EmitTarget::Sb3 => {
+            let project = try_parse_and_validate(&merged.text, &source_map, args.error_format)?;
+            let output = args
+                .output
+                .ok_or_else(|| anyhow::anyhow!("Missing output path. Pass OUTPUT, or use --emit tokens/ast/json/merged to dump without one."))?;
+            if args.no_python_backend {
+                sb3_backend::compile(&project, &output, args.no_svg_scale)?;
+            } else {
+                python_backend::compile_with_python(&input, &merged.text, &output, args.no_svg_scale)?;
+            }
+        }
     }
 
-    if !args.no_python_backend {
-        let output = args
-            .output
-            .ok_or_else(|| anyhow::anyhow!("Missing output path. Pass OUTPUT or use --emit-merged only."))?;
-        python_backend::compile_with_python(&input, &merged, &output, args.no_svg_scale)?;
-    } else if args.output.is_some() {
-        return Err(anyhow::anyhow!(
-            "OUTPUT was provided but --no-python-backend is set. \
-             Either remove OUTPUT or keep Python backend enabled."
-        ));
+    Ok(())
+}
+
+fn run_check(args: CheckArgs) -> anyhow::Result<()> {
+    let input = canonicalize_file(&args.input)?;
+    let merged = resolve_merged_source(&input, std_prelude(args.no_std))?;
+    let source_map = SourceMap::new(args.input.display().to_string(), merged.text.clone(), merged.origins);
+    try_parse_and_validate(&merged.text, &source_map, args.error_format)?;
+    println!("{}: ok", args.input.display());
+    Ok(())
+}
+
+fn run_list_std() -> anyhow::Result<()> {
+    for symbol in stdlib::symbols() {
+        println!("{symbol}");
     }
+    Ok(())
+}
+
+/// The stdlib source to merge in ahead of user imports, unless `--no-std` was passed.
+fn std_prelude(no_std: bool) -> Option<&'static str> {
+    (!no_std).then_some(stdlib::SOURCE)
+}
+
+fn run_new(args: NewArgs) -> anyhow::Result<()> {
+    let project_dir = args.path.join(&args.name);
+    std::fs::create_dir_all(&project_dir)
+        .map_err(|e| anyhow::anyhow!("creating project directory '{}': {e}", project_dir.display()))?;
 
+    let main_path = project_dir.join("main.sbtext");
+    if main_path.exists() {
+        return Err(anyhow::anyhow!("'{}' already exists", main_path.display()));
+    }
+    std::fs::write(&main_path, new_project_template(&args.name))?;
+
+    println!("Created '{}'", project_dir.display());
     Ok(())
 }
 
-fn validate_project(source: &str) -> Result<()> {
+fn new_project_template(name: &str) -> String {
+    format!(
+        "project \"{name}\"\n\nsprite Sprite1 {{\n    when_flag_clicked {{\n        say \"Hello, world!\"\n    }}\n}}\n"
+    )
+}
+
+fn emit_output(output: &Option<PathBuf>, content: &str) -> anyhow::Result<()> {
+    match output {
+        Some(path) => std::fs::write(path, content)?,
+        None => println!("{content}"),
+    }
+    Ok(())
+}
+
+/// Tokenizes `source`, reporting and converting a `Diagnostic` failure into
+/// the `Reported` sentinel via `source_map` rather than letting it bubble up
+/// to `main`'s generic `{err:#}` printer.
+fn try_tokenize(source: &str, source_map: &SourceMap, format: ErrorFormat) -> anyhow::Result<Vec<lexer::Token>> {
+    let mut lexer = Lexer::new(source);
+    lexer.tokenize().map_err(|err| report_or_pass_through(err, source_map, format))
+}
+
+fn try_parse_and_validate(source: &str, source_map: &SourceMap, format: ErrorFormat) -> anyhow::Result<ast::Project> {
+    parse_and_validate(source).map_err(|err| report_or_pass_through(err, source_map, format))
+}
+
+fn parse_and_validate(source: &str) -> anyhow::Result<ast::Project> {
     let mut lexer = Lexer::new(source);
     let tokens = lexer.tokenize()?;
     let mut parser = SbParser::new(tokens);
     let project = parser.parse_project()?;
     semantic_analyze(&project)?;
-    Ok(())
+    Ok(project)
+}
+
+fn report_or_pass_through(err: anyhow::Error, source_map: &SourceMap, format: ErrorFormat) -> anyhow::Error {
+    match err.downcast_ref::<Diagnostic>() {
+        Some(diagnostic) => source_map.report(diagnostic, format).into(),
+        None => err,
+    }
 }
 
-fn canonicalize_file(path: &PathBuf) -> Result<PathBuf> {
+fn canonicalize_file(path: &Path) -> anyhow::Result<PathBuf> {
     if !path.exists() || !path.is_file() {
         return Err(anyhow::anyhow!("Input file not found: '{}'.", path.display()));
     }
@@ -0,0 +1,807 @@
+//! Recursive-descent parser for SBText.
+//!
+//! Lowers the token stream directly into the final `ast::Project` shape
+//! that `sb3_backend` consumes: statements become `Block`
+//! chains linked via `next`, and `if`/`repeat`/`while` bodies become
+//! `InputValue::Substack` children, so no separate surface AST is needed.
+//!
+//! Grammar (informal):
+//! ```text
+//! project    := top_decl*
+//! top_decl   := ("project" STRING) | define | sprite
+//! define     := "define" IDENT "(" (IDENT ("," IDENT)*)? ")" block
+//! sprite     := "sprite" IDENT "{" script* "}"
+//! script     := "when_flag_clicked" block
+//! block      := "{" stmt* "}"
+//! stmt       := "say" expr
+//!             | "if" expr block
+//!             | "repeat" expr block
+//!             | "while" expr block
+//!             | "let" IDENT "=" expr
+//!             | "set" IDENT "to" expr
+//!             | "return" expr
+//! expr       := equality
+//! equality   := comparison ("==" comparison)*
+//! comparison := additive (("<" | ">") additive)*
+//! additive   := multiplicative (("+" | "-") multiplicative)*
+//! multiplicative := primary (("*" | "/") primary)*
+//! primary    := NUMBER | STRING | IDENT ("(" (expr ("," expr)*)? ")")? | "(" expr ")"
+//! ```
+
+use crate::ast::{self, Block, Field, Input, InputValue, Mutation, Project, Script, Target, Variable};
+use crate::diagnostics::{Diagnostic, Span};
+use crate::lexer::{Token, TokenKind};
+use anyhow::Result;
+
+/// The lowered value of an expression: either a literal shadow or a
+/// reporter block that must be inserted into the block map.
+enum Lowered {
+    Shadow(i32, String),
+    Block(Block),
+}
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    next_id: usize,
+    next_var_id: usize,
+    /// Variables registered by `let`/`set`/reads in the target currently
+    /// being parsed. Saved and reset around each sprite so sprites don't
+    /// share a variable namespace, then moved onto the finished `Target`.
+    target_variables: Vec<Variable>,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            next_id: 0,
+            next_var_id: 0,
+            target_variables: Vec::new(),
+        }
+    }
+
+    /// Returns the id of the `Variable` named `name` in the target
+    /// currently being parsed, registering a new one (first `let`/`set`/read
+    /// wins) if this is the first time it's been seen.
+    fn variable_id(&mut self, name: &str) -> String {
+        if let Some(variable) = self.target_variables.iter().find(|v| v.name == name) {
+            return variable.id.clone();
+        }
+        let id = format!("var{}", self.next_var_id);
+        self.next_var_id += 1;
+        self.target_variables.push(Variable {
+            id: id.clone(),
+            name: name.to_string(),
+            default: serde_json::json!(0),
+        });
+        id
+    }
+
+    pub fn parse_project(&mut self) -> Result<Project> {
+        let mut stage_scripts = Vec::new();
+        let mut sprites = Vec::new();
+
+        while !self.is_eof() {
+            if self.at_keyword("project") {
+                self.bump();
+                self.expect_kind(TokenKind::Str, "a project name string")?;
+            } else if self.at_keyword("define") {
+                stage_scripts.push(self.parse_define()?);
+            } else if self.at_keyword("sprite") {
+                sprites.push(self.parse_sprite()?);
+            } else {
+                return Err(self.error_here("E100", "expected 'project', 'define', or 'sprite'"));
+            }
+        }
+
+        let mut targets = vec![Target {
+            name: "Stage".to_string(),
+            is_stage: true,
+            span: Span::new(0, 0),
+            variables: std::mem::take(&mut self.target_variables),
+            lists: Vec::new(),
+            broadcasts: Vec::new(),
+            costumes: Vec::new(),
+            sounds: Vec::new(),
+            scripts: stage_scripts,
+        }];
+        targets.extend(sprites);
+        Ok(Project { targets })
+    }
+
+    fn parse_define(&mut self) -> Result<Script> {
+        let start = self.current_span().start;
+        self.bump(); // 'define'
+        let name = self.expect_kind(TokenKind::Ident, "a custom block name")?.text;
+        self.expect_punct("(")?;
+        let mut params = Vec::new();
+        if !self.at_punct(")") {
+            loop {
+                params.push(self.expect_kind(TokenKind::Ident, "a parameter name")?.text);
+                if self.at_punct(",") {
+                    self.bump();
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect_punct(")")?;
+        let body = self.parse_block_body()?;
+        let end = self.last_span_end(start);
+
+        let argument_ids: Vec<String> = (0..params.len()).map(|i| format!("ARG{i}")).collect();
+        let mutation = Mutation {
+            proccode: ast::proccode(&name, params.len()),
+            argument_ids: argument_ids.clone(),
+        };
+
+        let mut fields = vec![Field {
+            name: "NAME".to_string(),
+            value: name,
+            id: None,
+        }];
+        for (param, argument_id) in params.into_iter().zip(argument_ids) {
+            fields.push(Field {
+                name: argument_id,
+                value: param,
+                id: None,
+            });
+        }
+
+        Ok(Script {
+            root: Block {
+                id: self.fresh_id(),
+                opcode: "procedures_definition".to_string(),
+                span: Span::new(start, end),
+                inputs: Vec::new(),
+                fields,
+                mutation: Some(mutation),
+                shadow: false,
+                x: 0.0,
+                y: 0.0,
+                next: body.map(Box::new),
+            },
+        })
+    }
+
+    fn parse_sprite(&mut self) -> Result<Target> {
+        let start = self.current_span().start;
+        self.bump(); // 'sprite'
+        let name = self.expect_kind(TokenKind::Ident, "a sprite name")?.text;
+        self.expect_punct("{")?;
+        let outer_variables = std::mem::take(&mut self.target_variables);
+        let mut scripts = Vec::new();
+        while !self.at_punct("}") {
+            scripts.push(self.parse_script()?);
+        }
+        self.expect_punct("}")?;
+        let end = self.last_span_end(start);
+        let variables = std::mem::replace(&mut self.target_variables, outer_variables);
+
+        Ok(Target {
+            name,
+            is_stage: false,
+            span: Span::new(start, end),
+            variables,
+            lists: Vec::new(),
+            broadcasts: Vec::new(),
+            costumes: Vec::new(),
+            sounds: Vec::new(),
+            scripts,
+        })
+    }
+
+    fn parse_script(&mut self) -> Result<Script> {
+        let start = self.current_span().start;
+        let hat = self.expect_kind(TokenKind::Ident, "a hat block (e.g. 'when_flag_clicked')")?;
+        let opcode = match hat.text.as_str() {
+            "when_flag_clicked" => "event_whenflagclicked",
+            other => {
+                return Err(Diagnostic::error("E101", format!("unknown hat block '{other}'"), hat.span).into())
+            }
+        };
+        let body = self.parse_block_body()?;
+        let end = self.last_span_end(start);
+        Ok(Script {
+            root: Block {
+                id: self.fresh_id(),
+                opcode: opcode.to_string(),
+                span: Span::new(start, end),
+                inputs: Vec::new(),
+                fields: Vec::new(),
+                mutation: None,
+                shadow: false,
+                x: 0.0,
+                y: 0.0,
+                next: body.map(Box::new),
+            },
+        })
+    }
+
+    /// Parses a `{ ... }` block, chaining its statements via `next`.
+    /// Returns `None` for an empty body.
+    fn parse_block_body(&mut self) -> Result<Option<Block>> {
+        self.expect_punct("{")?;
+        let mut blocks = Vec::new();
+        while !self.at_punct("}") {
+            blocks.push(self.parse_stmt()?);
+        }
+        self.expect_punct("}")?;
+        Ok(chain(blocks))
+    }
+
+    fn parse_stmt(&mut self) -> Result<Block> {
+        let keyword = self.expect_kind(TokenKind::Ident, "a statement")?;
+        let start = keyword.span.start;
+        match keyword.text.as_str() {
+            "say" => {
+                let (message, span) = self.parse_expr()?;
+                Ok(Block {
+                    id: self.fresh_id(),
+                    opcode: "looks_say".to_string(),
+                    span: Span::new(start, span.end),
+                    inputs: vec![self.lower_input("MESSAGE", message)],
+                    fields: Vec::new(),
+                    mutation: None,
+                    shadow: false,
+                    x: 0.0,
+                    y: 0.0,
+                    next: None,
+                })
+            }
+            "if" => self.parse_conditional("control_if", "CONDITION", start),
+            "repeat" => self.parse_conditional("control_repeat", "TIMES", start),
+            "while" => self.parse_conditional("control_while", "CONDITION", start),
+            "let" => self.parse_assignment(start),
+            "set" => {
+                let name = self.expect_kind(TokenKind::Ident, "a variable name")?.text;
+                self.expect_keyword("to")?;
+                self.finish_assignment(name, start)
+            }
+            "return" => {
+                let (value, span) = self.parse_expr()?;
+                Ok(Block {
+                    id: self.fresh_id(),
+                    opcode: "procedures_return".to_string(),
+                    span: Span::new(start, span.end),
+                    inputs: vec![self.lower_input("VALUE", value)],
+                    fields: Vec::new(),
+                    mutation: None,
+                    shadow: false,
+                    x: 0.0,
+                    y: 0.0,
+                    next: None,
+                })
+            }
+            other => Err(Diagnostic::error("E102", format!("unknown statement '{other}'"), keyword.span).into()),
+        }
+    }
+
+    /// Shared shape for `if`/`repeat`/`while`: a leading expression plus a
+    /// body block, lowered to a `condition_input_name` input and (when the
+    /// body is non-empty) a `SUBSTACK` input.
+    fn parse_conditional(&mut self, opcode: &str, condition_input_name: &str, start: usize) -> Result<Block> {
+        let (condition, _) = self.parse_expr()?;
+        let body = self.parse_block_body()?;
+        let end = self.last_span_end(start);
+
+        let mut inputs = vec![self.lower_input(condition_input_name, condition)];
+        if let Some(body) = body {
+            inputs.push(Input {
+                name: "SUBSTACK".to_string(),
+                value: InputValue::Substack(Box::new(body)),
+            });
+        }
+
+        Ok(Block {
+            id: self.fresh_id(),
+            opcode: opcode.to_string(),
+            span: Span::new(start, end),
+            inputs,
+            fields: Vec::new(),
+            mutation: None,
+            shadow: false,
+            x: 0.0,
+            y: 0.0,
+            next: None,
+        })
+    }
+
+    fn parse_assignment(&mut self, start: usize) -> Result<Block> {
+        let name = self.expect_kind(TokenKind::Ident, "a variable name")?.text;
+        self.expect_punct("=")?;
+        self.finish_assignment(name, start)
+    }
+
+    fn finish_assignment(&mut self, variable: String, start: usize) -> Result<Block> {
+        let variable_id = self.variable_id(&variable);
+        let (value, span) = self.parse_expr()?;
+        Ok(Block {
+            id: self.fresh_id(),
+            opcode: "data_setvariableto".to_string(),
+            span: Span::new(start, span.end),
+            inputs: vec![self.lower_input("VALUE", value)],
+            fields: vec![Field {
+                name: "VARIABLE".to_string(),
+                value: variable,
+                id: Some(variable_id),
+            }],
+            mutation: None,
+            shadow: false,
+            x: 0.0,
+            y: 0.0,
+            next: None,
+        })
+    }
+
+    fn parse_expr(&mut self) -> Result<(Lowered, Span)> {
+        self.parse_equality()
+    }
+
+    fn parse_equality(&mut self) -> Result<(Lowered, Span)> {
+        let mut lhs = self.parse_comparison()?;
+        while self.at_punct("==") {
+            self.bump();
+            let rhs = self.parse_comparison()?;
+            lhs = self.binop("operator_equals", lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_comparison(&mut self) -> Result<(Lowered, Span)> {
+        let mut lhs = self.parse_additive()?;
+        loop {
+            let opcode = if self.at_punct("<") {
+                "operator_lt"
+            } else if self.at_punct(">") {
+                "operator_gt"
+            } else {
+                break;
+            };
+            self.bump();
+            let rhs = self.parse_additive()?;
+            lhs = self.binop(opcode, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_additive(&mut self) -> Result<(Lowered, Span)> {
+        let mut lhs = self.parse_multiplicative()?;
+        loop {
+            let opcode = if self.at_punct("+") {
+                "operator_add"
+            } else if self.at_punct("-") {
+                "operator_subtract"
+            } else {
+                break;
+            };
+            self.bump();
+            let rhs = self.parse_multiplicative()?;
+            lhs = self.binop(opcode, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<(Lowered, Span)> {
+        let mut lhs = self.parse_primary()?;
+        loop {
+            let opcode = if self.at_punct("*") {
+                "operator_multiply"
+            } else if self.at_punct("/") {
+                "operator_divide"
+            } else {
+                break;
+            };
+            self.bump();
+            let rhs = self.parse_primary()?;
+            lhs = self.binop(opcode, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_primary(&mut self) -> Result<(Lowered, Span)> {
+        let tok = self.peek().cloned().ok_or_else(|| self.error_here("E103", "expected an expression"))?;
+        match tok.kind {
+            TokenKind::Number => {
+                self.bump();
+                Ok((Lowered::Shadow(4, tok.text), tok.span))
+            }
+            TokenKind::Str => {
+                self.bump();
+                Ok((Lowered::Shadow(10, tok.text), tok.span))
+            }
+            TokenKind::Ident => {
+                self.bump();
+                if self.at_punct("(") {
+                    self.bump();
+                    let mut args = Vec::new();
+                    if !self.at_punct(")") {
+                        loop {
+                            args.push(self.parse_expr()?);
+                            if self.at_punct(",") {
+                                self.bump();
+                            } else {
+                                break;
+                            }
+                        }
+                    }
+                    let close = self.expect_punct(")")?;
+                    let span = Span::new(tok.span.start, close.end);
+                    if let Some(lowered) = self.lower_builtin_call(&tok.text, &mut args, span) {
+                        return Ok((lowered, span));
+                    }
+                    let argument_ids: Vec<String> = (0..args.len()).map(|i| format!("ARG{i}")).collect();
+                    let inputs = args
+                        .into_iter()
+                        .zip(&argument_ids)
+                        .map(|((value, _), argument_id)| self.lower_input(argument_id, value))
+                        .collect();
+                    let mutation = Mutation {
+                        proccode: ast::proccode(&tok.text, argument_ids.len()),
+                        argument_ids,
+                    };
+                    Ok((
+                        Lowered::Block(Block {
+                            id: self.fresh_id(),
+                            opcode: "procedures_call".to_string(),
+                            span,
+                            inputs,
+                            fields: Vec::new(),
+                            mutation: Some(mutation),
+                            shadow: false,
+                            x: 0.0,
+                            y: 0.0,
+                            next: None,
+                        }),
+                        span,
+                    ))
+                } else {
+                    let variable_id = self.variable_id(&tok.text);
+                    Ok((
+                        Lowered::Block(Block {
+                            id: self.fresh_id(),
+                            opcode: "data_variable".to_string(),
+                            span: tok.span,
+                            inputs: Vec::new(),
+                            fields: vec![Field {
+                                name: "VARIABLE".to_string(),
+                                value: tok.text,
+                                id: Some(variable_id),
+                            }],
+                            mutation: None,
+                            shadow: false,
+                            x: 0.0,
+                            y: 0.0,
+                            next: None,
+                        }),
+                        tok.span,
+                    ))
+                }
+            }
+            TokenKind::Punct if tok.text == "(" => {
+                self.bump();
+                let inner = self.parse_expr()?;
+                self.expect_punct(")")?;
+                Ok(inner)
+            }
+            _ => Err(self.error_here("E103", "expected an expression")),
+        }
+    }
+
+    fn binop(&mut self, opcode: &str, lhs: (Lowered, Span), rhs: (Lowered, Span)) -> (Lowered, Span) {
+        let span = Span::new(lhs.1.start, rhs.1.end);
+        let inputs = vec![self.lower_input("A", lhs.0), self.lower_input("B", rhs.0)];
+        (
+            Lowered::Block(Block {
+                id: self.fresh_id(),
+                opcode: opcode.to_string(),
+                span,
+                inputs,
+                fields: Vec::new(),
+                mutation: None,
+                shadow: false,
+                x: 0.0,
+                y: 0.0,
+                next: None,
+            }),
+            span,
+        )
+    }
+
+    /// Lowers a call to one of the handful of names the Scratch VM has a
+    /// real native opcode for, so they run as themselves instead of as a
+    /// `procedures_call` to a custom block nothing ever `define`s (which the
+    /// VM would silently no-op). Returns `None` for any other name, leaving
+    /// it to the caller to lower as an ordinary custom-block call; `args` is
+    /// only drained when a name+arity match is found.
+    fn lower_builtin_call(&mut self, name: &str, args: &mut Vec<(Lowered, Span)>, span: Span) -> Option<Lowered> {
+        match (name, args.len()) {
+            ("round", 1) => {
+                let value = args.pop().unwrap();
+                Some(self.native_unary("operator_round", "NUM", value, span))
+            }
+            ("length_of", 1) => {
+                let value = args.pop().unwrap();
+                Some(self.native_unary("operator_length", "STRING", value, span))
+            }
+            ("pow", 2) => {
+                let exponent = args.pop().unwrap();
+                let base = args.pop().unwrap();
+                Some(self.lower_pow(base, exponent, span))
+            }
+            _ => None,
+        }
+    }
+
+    fn native_unary(&mut self, opcode: &str, input_name: &str, value: (Lowered, Span), span: Span) -> Lowered {
+        let input = self.lower_input(input_name, value.0);
+        Lowered::Block(Block {
+            id: self.fresh_id(),
+            opcode: opcode.to_string(),
+            span,
+            inputs: vec![input],
+            fields: Vec::new(),
+            mutation: None,
+            shadow: false,
+            x: 0.0,
+            y: 0.0,
+            next: None,
+        })
+    }
+
+    /// `base ^ exponent` has no native opcode (Scratch's `operator_mathop`
+    /// only offers fixed-base `e ^`/`10 ^`), so it's lowered to the
+    /// equivalent `10 ^ (exponent * log10(base))`, built entirely out of
+    /// native `operator_mathop`/`operator_multiply` blocks. Only valid for
+    /// `base > 0`, same as the real `log10` it's built from.
+    fn lower_pow(&mut self, base: (Lowered, Span), exponent: (Lowered, Span), span: Span) -> Lowered {
+        let log_base = self.mathop("log", base, span);
+        let (scaled, _) = self.binop("operator_multiply", exponent, (log_base, span));
+        self.mathop("10 ^", (scaled, span), span)
+    }
+
+    fn mathop(&mut self, operator: &str, value: (Lowered, Span), span: Span) -> Lowered {
+        let input = self.lower_input("NUM", value.0);
+        Lowered::Block(Block {
+            id: self.fresh_id(),
+            opcode: "operator_mathop".to_string(),
+            span,
+            inputs: vec![input],
+            fields: vec![Field {
+                name: "OPERATOR".to_string(),
+                value: operator.to_string(),
+                id: None,
+            }],
+            mutation: None,
+            shadow: false,
+            x: 0.0,
+            y: 0.0,
+            next: None,
+        })
+    }
+
+    fn lower_input(&self, name: &str, lowered: Lowered) -> Input {
+        let value = match lowered {
+            Lowered::Shadow(type_code, value) => InputValue::Shadow { type_code, value },
+            Lowered::Block(block) => InputValue::Reporter(Box::new(block)),
+        };
+        Input {
+            name: name.to_string(),
+            value,
+        }
+    }
+
+    fn fresh_id(&mut self) -> String {
+        let id = format!("b{}", self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    fn last_span_end(&self, fallback: usize) -> usize {
+        self.pos
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map(|t| t.span.end)
+            .unwrap_or(fallback)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn is_eof(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        if tok.is_some() {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(t) if t.kind == TokenKind::Ident && t.text == keyword)
+    }
+
+    fn at_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(t) if t.kind == TokenKind::Punct && t.text == punct)
+    }
+
+    fn expect_kind(&mut self, kind: TokenKind, what: &str) -> Result<Token> {
+        match self.peek() {
+            Some(t) if t.kind == kind => Ok(self.bump().unwrap()),
+            _ => Err(self.error_here("E104", format!("expected {what}"))),
+        }
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<Span> {
+        match self.peek() {
+            Some(t) if t.kind == TokenKind::Punct && t.text == punct => Ok(self.bump().unwrap().span),
+            _ => Err(self.error_here("E105", format!("expected '{punct}'"))),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<Span> {
+        match self.peek() {
+            Some(t) if t.kind == TokenKind::Ident && t.text == keyword => Ok(self.bump().unwrap().span),
+            _ => Err(self.error_here("E106", format!("expected '{keyword}'"))),
+        }
+    }
+
+    fn error_here(&self, code: &'static str, message: impl Into<String>) -> anyhow::Error {
+        let span = self.current_span();
+        Diagnostic::error(code, message, span).into()
+    }
+
+    fn current_span(&self) -> Span {
+        self.peek()
+            .map(|t| t.span)
+            .unwrap_or_else(|| self.tokens.last().map(|t| Span::new(t.span.end, t.span.end)).unwrap_or(Span::new(0, 0)))
+    }
+}
+
+/// Chains `blocks` into a single list via `next`, returning the first block.
+fn chain(mut blocks: Vec<Block>) -> Option<Block> {
+    let mut tail = None;
+    while let Some(mut block) = blocks.pop() {
+        block.next = tail.map(Box::new);
+        tail = Some(block);
+    }
+    tail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn parse(source: &str) -> Project {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.tokenize().unwrap();
+        Parser::new(tokens).parse_project().unwrap()
+    }
+
+    #[test]
+    fn parses_a_hello_world_sprite() {
+        let project = parse("project \"demo\"\n\nsprite Sprite1 {\n    when_flag_clicked {\n        say \"hi\"\n    }\n}\n");
+        let sprite = project.targets.iter().find(|t| !t.is_stage).unwrap();
+        assert_eq!(sprite.name, "Sprite1");
+        assert_eq!(sprite.scripts.len(), 1);
+        assert_eq!(sprite.scripts[0].root.opcode, "event_whenflagclicked");
+        let say = sprite.scripts[0].root.next.as_ref().unwrap();
+        assert_eq!(say.opcode, "looks_say");
+    }
+
+    #[test]
+    fn parses_an_if_block_into_a_substack() {
+        let project = parse("sprite S { when_flag_clicked { if 1 < 2 { say \"yes\" } } }");
+        let sprite = &project.targets[1];
+        let if_block = sprite.scripts[0].root.next.as_ref().unwrap();
+        assert_eq!(if_block.opcode, "control_if");
+        let substack = if_block.inputs.iter().find(|i| i.name == "SUBSTACK").unwrap();
+        match &substack.value {
+            InputValue::Substack(body) => assert_eq!(body.opcode, "looks_say"),
+            other => panic!("expected a substack, got {other:?}"),
+        }
+    }
+
+    /// Regression test: a `project "name"` header can land anywhere among
+    /// the top-level declarations, not just at the very start of the buffer.
+    #[test]
+    fn parses_a_project_header_that_is_not_the_first_declaration() {
+        let project = parse("define helper() { return 1 }\nproject \"demo\"\n\nsprite Sprite1 { when_flag_clicked { say \"hi\" } }\n");
+        assert_eq!(project.targets.len(), 2);
+    }
+
+    /// Regression test: `let`/`set` used to never register a `Variable` on
+    /// the owning `Target`, so `VARIABLE` fields always pointed at a `None`
+    /// id even though `Target.variables` was supposed to hold the id.
+    #[test]
+    fn registers_a_variable_on_first_assignment() {
+        let project = parse("sprite S { when_flag_clicked { let x = 1\nset x to 2\nsay x } }");
+        let sprite = &project.targets[1];
+        assert_eq!(sprite.variables.len(), 1);
+        let var_id = sprite.variables[0].id.clone();
+        assert_eq!(sprite.variables[0].name, "x");
+
+        let let_block = &sprite.scripts[0].root.next.as_ref().unwrap();
+        let let_field = let_block.fields.iter().find(|f| f.name == "VARIABLE").unwrap();
+        assert_eq!(let_field.id, Some(var_id.clone()));
+
+        let set_block = let_block.next.as_ref().unwrap();
+        let set_field = set_block.fields.iter().find(|f| f.name == "VARIABLE").unwrap();
+        assert_eq!(set_field.id, Some(var_id.clone()));
+
+        let say_block = set_block.next.as_ref().unwrap();
+        let say_arg = say_block.inputs.iter().find(|i| i.name == "MESSAGE").unwrap();
+        match &say_arg.value {
+            InputValue::Reporter(reporter) => {
+                let field = reporter.fields.iter().find(|f| f.name == "VARIABLE").unwrap();
+                assert_eq!(field.id, Some(var_id));
+            }
+            other => panic!("expected a data_variable reporter, got {other:?}"),
+        }
+    }
+
+    /// Regression test: sprites don't share a variable namespace — the same
+    /// name in two sprites must register two distinct ids.
+    #[test]
+    fn gives_distinct_sprites_distinct_variable_ids() {
+        let project = parse(
+            "sprite A { when_flag_clicked { let x = 1 } }\nsprite B { when_flag_clicked { let x = 2 } }\n",
+        );
+        let a = &project.targets[1];
+        let b = &project.targets[2];
+        assert_ne!(a.variables[0].id, b.variables[0].id);
+    }
+
+    /// Regression test: `round`/`length_of`/`pow` used to lower to a
+    /// `procedures_call` to a custom block nothing ever `define`s, which the
+    /// real Scratch VM silently no-ops. They must lower to real opcodes.
+    #[test]
+    fn lowers_round_to_a_native_operator() {
+        let project = parse("sprite S { when_flag_clicked { say round(1.5) } }");
+        let say = &project.targets[1].scripts[0].root.next.as_ref().unwrap();
+        let arg = say.inputs.iter().find(|i| i.name == "MESSAGE").unwrap();
+        match &arg.value {
+            InputValue::Reporter(reporter) => assert_eq!(reporter.opcode, "operator_round"),
+            other => panic!("expected an operator_round reporter, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn lowers_length_of_to_a_native_operator() {
+        let project = parse("sprite S { when_flag_clicked { say length_of(\"hi\") } }");
+        let say = &project.targets[1].scripts[0].root.next.as_ref().unwrap();
+        let arg = say.inputs.iter().find(|i| i.name == "MESSAGE").unwrap();
+        match &arg.value {
+            InputValue::Reporter(reporter) => assert_eq!(reporter.opcode, "operator_length"),
+            other => panic!("expected an operator_length reporter, got {other:?}"),
+        }
+    }
+
+    /// `pow` has no native opcode, so it lowers to a small tree of native
+    /// `operator_mathop`/`operator_multiply` blocks rather than a single one.
+    #[test]
+    fn lowers_pow_to_native_mathop_blocks() {
+        let project = parse("sprite S { when_flag_clicked { say pow(10, 2) } }");
+        let say = &project.targets[1].scripts[0].root.next.as_ref().unwrap();
+        let arg = say.inputs.iter().find(|i| i.name == "MESSAGE").unwrap();
+        let outer = match &arg.value {
+            InputValue::Reporter(reporter) => reporter,
+            other => panic!("expected a reporter, got {other:?}"),
+        };
+        assert_eq!(outer.opcode, "operator_mathop");
+        let operator = outer.fields.iter().find(|f| f.name == "OPERATOR").unwrap();
+        assert_eq!(operator.value, "10 ^");
+        let scaled = outer.inputs.iter().find(|i| i.name == "NUM").unwrap();
+        match &scaled.value {
+            InputValue::Reporter(multiply) => assert_eq!(multiply.opcode, "operator_multiply"),
+            other => panic!("expected an operator_multiply reporter, got {other:?}"),
+        }
+    }
+}
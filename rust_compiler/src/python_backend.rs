@@ -0,0 +1,46 @@
+//! Backend that shells out to the existing Python SBText compiler.
+//!
+//! This is the long-standing default (`sb3_backend` is the newer native
+//! alternative enabled via `--no-python-backend`). Import resolution has
+//! already happened in Rust by the time `compile_with_python` runs, so the
+//! already-merged source is piped in on stdin rather than having the Python
+//! side re-resolve imports itself.
+
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Compiles `merged` to `output` via the Python `sbtext` compiler.
+pub fn compile_with_python(input: &Path, merged: &str, output: &Path, no_svg_scale: bool) -> Result<()> {
+    let mut command = Command::new("python3");
+    command
+        .arg("-m")
+        .arg("sbtext.compile")
+        .arg("--source-name")
+        .arg(input.display().to_string())
+        .arg("--output")
+        .arg(output)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    if no_svg_scale {
+        command.arg("--no-svg-scale");
+    }
+
+    let mut child = command
+        .spawn()
+        .context("spawning the Python sbtext compiler (is it on PATH?)")?;
+    child
+        .stdin
+        .as_mut()
+        .expect("stdin was piped")
+        .write_all(merged.as_bytes())
+        .context("writing merged source to the Python sbtext compiler")?;
+
+    let status = child.wait().context("waiting for the Python sbtext compiler")?;
+    if !status.success() {
+        bail!("Python sbtext compiler exited with {status}");
+    }
+    Ok(())
+}
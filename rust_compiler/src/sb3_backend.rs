@@ -0,0 +1,369 @@
+//! Native Rust backend for `.sb3` output.
+//!
+//! Produces the same artifact as `python_backend::compile_with_python` directly
+//! from the `ast::Project` returned by `parser::parse_project`, so `.sb3`
+//! output no longer requires a Python install. An `.sb3` is a ZIP archive
+//! containing a Scratch-VM-format `project.json` plus the costume/sound
+//! assets it references.
+
+use crate::ast::{Block, Costume, Field, Input, InputValue, Mutation, Project, Sound, Target};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Compiles `project` to a native `.sb3` archive at `output`.
+///
+/// SVG costumes are normalized to 64x64 unless `no_svg_scale` is set, matching
+/// the behavior of the Python backend.
+pub fn compile(project: &Project, output: &Path, no_svg_scale: bool) -> Result<()> {
+    let project_json = build_project_json(project);
+    let body = serde_json::to_vec(&project_json).context("serializing project.json")?;
+
+    let file = std::fs::File::create(output)
+        .with_context(|| format!("creating sb3 output '{}'", output.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("project.json", options)
+        .context("writing project.json entry")?;
+    zip.write_all(&body)?;
+
+    for target in project.all_targets() {
+        for costume in &target.costumes {
+            write_costume(&mut zip, options, costume, no_svg_scale)?;
+        }
+        for sound in &target.sounds {
+            write_sound(&mut zip, options, sound)?;
+        }
+    }
+
+    zip.finish().context("finalizing sb3 archive")?;
+    Ok(())
+}
+
+fn write_costume(
+    zip: &mut ZipWriter<std::fs::File>,
+    options: FileOptions,
+    costume: &Costume,
+    no_svg_scale: bool,
+) -> Result<()> {
+    let data = if costume.data_format == "svg" && !no_svg_scale {
+        crate::svg::normalize_to_64x64(&costume.data)?
+    } else {
+        costume.data.clone()
+    };
+    zip.start_file(&costume.md5ext, options)
+        .with_context(|| format!("writing costume asset '{}'", costume.md5ext))?;
+    zip.write_all(&data)?;
+    Ok(())
+}
+
+fn write_sound(zip: &mut ZipWriter<std::fs::File>, options: FileOptions, sound: &Sound) -> Result<()> {
+    zip.start_file(&sound.md5ext, options)
+        .with_context(|| format!("writing sound asset '{}'", sound.md5ext))?;
+    zip.write_all(&sound.data)?;
+    Ok(())
+}
+
+/// A Scratch VM `project.json` document, built deterministically so repeated
+/// builds of the same `Project` produce byte-identical output.
+#[derive(Serialize)]
+struct ProjectJson {
+    targets: Vec<TargetJson>,
+    meta: MetaJson,
+}
+
+#[derive(Serialize)]
+struct MetaJson {
+    semver: &'static str,
+    #[serde(rename = "agent")]
+    agent: &'static str,
+}
+
+#[derive(Serialize)]
+struct TargetJson {
+    #[serde(rename = "isStage")]
+    is_stage: bool,
+    name: String,
+    variables: BTreeMap<String, (String, serde_json::Value)>,
+    lists: BTreeMap<String, (String, Vec<serde_json::Value>)>,
+    broadcasts: BTreeMap<String, String>,
+    blocks: BTreeMap<String, BlockJson>,
+    costumes: Vec<CostumeJson>,
+    sounds: Vec<SoundJson>,
+    #[serde(rename = "currentCostume")]
+    current_costume: u32,
+}
+
+#[derive(Serialize)]
+struct BlockJson {
+    opcode: String,
+    next: Option<String>,
+    parent: Option<String>,
+    inputs: BTreeMap<String, serde_json::Value>,
+    fields: BTreeMap<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mutation: Option<MutationJson>,
+    shadow: bool,
+    #[serde(rename = "topLevel")]
+    top_level: bool,
+    x: f64,
+    y: f64,
+}
+
+/// The real Scratch VM `mutation` shape: an XML-element-like object
+/// carrying the `PROCCODE` that ties a `procedures_call` to its
+/// `procedures_definition`, with the argument list JSON-encoded as a string
+/// (matching how the Scratch VM itself serializes it).
+#[derive(Serialize)]
+struct MutationJson {
+    #[serde(rename = "tagName")]
+    tag_name: &'static str,
+    children: Vec<()>,
+    proccode: String,
+    #[serde(rename = "argumentids")]
+    argument_ids: String,
+    warp: &'static str,
+}
+
+fn build_mutation_json(mutation: &Mutation) -> MutationJson {
+    MutationJson {
+        tag_name: "mutation",
+        children: Vec::new(),
+        proccode: mutation.proccode.clone(),
+        argument_ids: serde_json::to_string(&mutation.argument_ids).expect("argument ids always serialize"),
+        warp: "false",
+    }
+}
+
+#[derive(Serialize)]
+struct CostumeJson {
+    name: String,
+    #[serde(rename = "assetId")]
+    asset_id: String,
+    #[serde(rename = "dataFormat")]
+    data_format: String,
+    #[serde(rename = "md5ext")]
+    md5ext: String,
+    #[serde(rename = "rotationCenterX")]
+    rotation_center_x: f64,
+    #[serde(rename = "rotationCenterY")]
+    rotation_center_y: f64,
+}
+
+#[derive(Serialize)]
+struct SoundJson {
+    name: String,
+    #[serde(rename = "assetId")]
+    asset_id: String,
+    #[serde(rename = "dataFormat")]
+    data_format: String,
+    #[serde(rename = "md5ext")]
+    md5ext: String,
+    rate: u32,
+    #[serde(rename = "sampleCount")]
+    sample_count: u32,
+}
+
+fn build_project_json(project: &Project) -> ProjectJson {
+    let targets = project.all_targets().map(build_target_json).collect();
+
+    ProjectJson {
+        targets,
+        meta: MetaJson {
+            semver: "3.0.0",
+            agent: "sbtext-rs/sb3_backend",
+        },
+    }
+}
+
+fn build_target_json(target: &Target) -> TargetJson {
+    let mut blocks = BTreeMap::new();
+    for script in &target.scripts {
+        flatten_block(&script.root, None, true, &mut blocks);
+    }
+
+    TargetJson {
+        is_stage: target.is_stage,
+        name: target.name.clone(),
+        variables: target
+            .variables
+            .iter()
+            .map(|v| (v.id.clone(), (v.name.clone(), v.default.clone())))
+            .collect(),
+        lists: target
+            .lists
+            .iter()
+            .map(|l| (l.id.clone(), (l.name.clone(), l.default.clone())))
+            .collect(),
+        broadcasts: target
+            .broadcasts
+            .iter()
+            .map(|b| (b.id.clone(), b.name.clone()))
+            .collect(),
+        blocks,
+        costumes: target.costumes.iter().map(build_costume_json).collect(),
+        sounds: target.sounds.iter().map(build_sound_json).collect(),
+        current_costume: 0,
+    }
+}
+
+fn flatten_block(
+    block: &Block,
+    parent: Option<&str>,
+    top_level: bool,
+    out: &mut BTreeMap<String, BlockJson>,
+) {
+    let next_id = block.next.as_ref().map(|next| next.id.clone());
+    if let Some(next) = &block.next {
+        flatten_block(next, Some(&block.id), false, out);
+    }
+
+    let inputs = block
+        .inputs
+        .iter()
+        .map(|input| input_entry(input, &block.id, out))
+        .collect();
+
+    out.insert(
+        block.id.clone(),
+        BlockJson {
+            opcode: block.opcode.clone(),
+            next: next_id,
+            parent: parent.map(str::to_string),
+            inputs,
+            fields: block.fields.iter().map(field_entry).collect(),
+            mutation: block.mutation.as_ref().map(build_mutation_json),
+            shadow: block.shadow,
+            top_level,
+            x: block.x,
+            y: block.y,
+        },
+    );
+}
+
+/// Lowers one `Input` to its sb3 encoding. `Reporter`/`Substack` inputs
+/// reference a child block by id, so the child is flattened into `out`
+/// first (as a non-top-level block parented to `block`), matching how
+/// C-blocks (`if`, `repeat`, `forever`, ...) attach their body.
+fn input_entry(input: &Input, parent_block_id: &str, out: &mut BTreeMap<String, BlockJson>) -> (String, serde_json::Value) {
+    let encoding = match &input.value {
+        InputValue::Shadow { type_code, value } => {
+            serde_json::json!([1, [type_code, value]])
+        }
+        InputValue::Reporter(block) => {
+            flatten_block(block, Some(parent_block_id), false, out);
+            serde_json::json!([2, block.id])
+        }
+        InputValue::Substack(block) => {
+            flatten_block(block, Some(parent_block_id), false, out);
+            serde_json::json!([2, block.id])
+        }
+    };
+    (input.name.clone(), encoding)
+}
+
+fn field_entry(field: &Field) -> (String, serde_json::Value) {
+    (field.name.clone(), serde_json::json!([field.value, field.id]))
+}
+
+fn build_costume_json(costume: &Costume) -> CostumeJson {
+    CostumeJson {
+        name: costume.name.clone(),
+        asset_id: costume.asset_id.clone(),
+        data_format: costume.data_format.clone(),
+        md5ext: costume.md5ext.clone(),
+        rotation_center_x: costume.rotation_center_x,
+        rotation_center_y: costume.rotation_center_y,
+    }
+}
+
+fn build_sound_json(sound: &Sound) -> SoundJson {
+    SoundJson {
+        name: sound.name.clone(),
+        asset_id: sound.asset_id.clone(),
+        data_format: sound.data_format.clone(),
+        md5ext: sound.md5ext.clone(),
+        rate: sound.rate,
+        sample_count: sound.sample_count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{InputValue, Script, Target};
+    use crate::diagnostics::Span;
+
+    fn leaf_block(id: &str, opcode: &str) -> Block {
+        Block {
+            id: id.to_string(),
+            opcode: opcode.to_string(),
+            span: Span::new(0, 0),
+            inputs: vec![Input {
+                name: "MESSAGE".to_string(),
+                value: InputValue::Shadow {
+                    type_code: 10,
+                    value: "hi".to_string(),
+                },
+            }],
+            fields: vec![],
+            mutation: None,
+            shadow: false,
+            x: 0.0,
+            y: 0.0,
+            next: None,
+        }
+    }
+
+    /// A `control_if` block whose body (`SUBSTACK`) is another block should
+    /// flatten both blocks into the map, not just the outer one.
+    #[test]
+    fn flatten_block_recurses_into_substack_inputs() {
+        let body = leaf_block("say1", "looks_say");
+        let if_block = Block {
+            id: "if1".to_string(),
+            opcode: "control_if".to_string(),
+            span: Span::new(0, 0),
+            inputs: vec![Input {
+                name: "SUBSTACK".to_string(),
+                value: InputValue::Substack(Box::new(body)),
+            }],
+            fields: vec![],
+            mutation: None,
+            shadow: false,
+            x: 0.0,
+            y: 0.0,
+            next: None,
+        };
+
+        let target = Target {
+            name: "Sprite1".to_string(),
+            is_stage: false,
+            span: Span::new(0, 0),
+            variables: vec![],
+            lists: vec![],
+            broadcasts: vec![],
+            costumes: vec![],
+            sounds: vec![],
+            scripts: vec![Script { root: if_block }],
+        };
+
+        let json = build_target_json(&target);
+
+        assert_eq!(json.blocks.len(), 2, "expected both the if block and its substack body to be flattened");
+
+        let if_json = &json.blocks["if1"];
+        assert!(if_json.top_level);
+        assert_eq!(if_json.parent, None);
+        assert_eq!(if_json.inputs["SUBSTACK"], serde_json::json!([2, "say1"]));
+
+        let say_json = &json.blocks["say1"];
+        assert!(!say_json.top_level);
+        assert_eq!(say_json.parent, Some("if1".to_string()));
+    }
+}
@@ -0,0 +1,181 @@
+//! Semantic checks that run after parsing, before a project is considered
+//! valid for `--emit ast/json/sb3`.
+//!
+//! These operate on the whole `ast::Project` rather than a single parse
+//! rule, since duplicate names are only visible once every sprite/define
+//! has been seen. Diagnostics carry the offending `Target`/`Block`'s span so
+//! `--error-format` can point at the duplicate, not just name it.
+
+use crate::ast::{Block, InputValue, Project};
+use crate::diagnostics::Diagnostic;
+use anyhow::Result;
+use std::collections::HashSet;
+
+pub fn analyze(project: &Project) -> Result<()> {
+    check_duplicate_sprite_names(project)?;
+    check_duplicate_define_names(project)?;
+    check_undefined_calls(project)?;
+    Ok(())
+}
+
+fn check_duplicate_sprite_names(project: &Project) -> Result<()> {
+    let mut seen = HashSet::new();
+    for target in project.all_targets().filter(|t| !t.is_stage) {
+        if !seen.insert(target.name.as_str()) {
+            return Err(Diagnostic::error(
+                "E200",
+                format!("duplicate sprite name '{}'", target.name),
+                target.span,
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn check_duplicate_define_names(project: &Project) -> Result<()> {
+    let mut seen = HashSet::new();
+    for target in project.all_targets() {
+        for script in &target.scripts {
+            let block = &script.root;
+            if block.opcode != "procedures_definition" {
+                continue;
+            }
+            let Some(name) = block.fields.iter().find(|f| f.name == "NAME") else {
+                continue;
+            };
+            if !seen.insert(name.value.clone()) {
+                return Err(Diagnostic::error(
+                    "E201",
+                    format!("duplicate custom block name '{}'", name.value),
+                    block.span,
+                )
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rejects `procedures_call` blocks whose `PROCCODE` doesn't match any
+/// `procedures_definition` in the project. Custom blocks are global (not
+/// scoped to a sprite), matching `check_duplicate_define_names` treating
+/// define names as unique project-wide.
+fn check_undefined_calls(project: &Project) -> Result<()> {
+    let mut defined = HashSet::new();
+    for target in project.all_targets() {
+        for script in &target.scripts {
+            walk_blocks(&script.root, &mut |block| {
+                if block.opcode == "procedures_definition" {
+                    if let Some(mutation) = &block.mutation {
+                        defined.insert(mutation.proccode.clone());
+                    }
+                }
+            });
+        }
+    }
+
+    for target in project.all_targets() {
+        for script in &target.scripts {
+            let mut undefined_call = None;
+            walk_blocks(&script.root, &mut |block| {
+                if undefined_call.is_some() || block.opcode != "procedures_call" {
+                    return;
+                }
+                if let Some(mutation) = &block.mutation {
+                    if !defined.contains(&mutation.proccode) {
+                        undefined_call = Some((mutation.proccode.clone(), block.span));
+                    }
+                }
+            });
+            if let Some((proccode, span)) = undefined_call {
+                let name = proccode.split(' ').next().unwrap_or(&proccode);
+                return Err(Diagnostic::error("E202", format!("call to undefined custom block '{name}'"), span).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Visits `block`, everything chained after it via `next`, and every block
+/// nested in a `Reporter`/`Substack` input, depth-first.
+fn walk_blocks<'a>(block: &'a Block, visit: &mut impl FnMut(&'a Block)) {
+    visit(block);
+    for input in &block.inputs {
+        match &input.value {
+            InputValue::Reporter(child) | InputValue::Substack(child) => walk_blocks(child, visit),
+            InputValue::Shadow { .. } => {}
+        }
+    }
+    if let Some(next) = &block.next {
+        walk_blocks(next, visit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn parse(source: &str) -> Project {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.tokenize().unwrap();
+        Parser::new(tokens).parse_project().unwrap()
+    }
+
+    #[test]
+    fn accepts_distinct_sprite_and_define_names() {
+        let project = parse(
+            "define helper() { return 1 }\nsprite A { when_flag_clicked { say \"hi\" } }\nsprite B { when_flag_clicked { say \"hi\" } }\n",
+        );
+        analyze(&project).unwrap();
+    }
+
+    #[test]
+    fn rejects_duplicate_sprite_names() {
+        let project = parse(
+            "sprite A { when_flag_clicked { say \"hi\" } }\nsprite A { when_flag_clicked { say \"hi\" } }\n",
+        );
+        let err = analyze(&project).unwrap_err();
+        let diagnostic = err.downcast_ref::<Diagnostic>().unwrap();
+        assert_eq!(diagnostic.code, "E200");
+    }
+
+    #[test]
+    fn rejects_duplicate_define_names() {
+        let project = parse("define helper() { return 1 }\ndefine helper() { return 2 }\n");
+        let err = analyze(&project).unwrap_err();
+        let diagnostic = err.downcast_ref::<Diagnostic>().unwrap();
+        assert_eq!(diagnostic.code, "E201");
+    }
+
+    #[test]
+    fn accepts_a_call_to_a_defined_custom_block() {
+        let project = parse(
+            "define helper(x) { return x }\nsprite A { when_flag_clicked { say helper(1) } }\n",
+        );
+        analyze(&project).unwrap();
+    }
+
+    /// Regression test: calls used to lower to a made-up `stdlib_call_<name>`
+    /// opcode with no check that the callee was ever defined.
+    #[test]
+    fn rejects_a_call_to_an_undefined_custom_block() {
+        let project = parse("sprite A { when_flag_clicked { say mystery(1) } }\n");
+        let err = analyze(&project).unwrap_err();
+        let diagnostic = err.downcast_ref::<Diagnostic>().unwrap();
+        assert_eq!(diagnostic.code, "E202");
+        assert!(diagnostic.message.contains("mystery"));
+    }
+
+    /// The bundled stdlib is merged into every default build, so it must
+    /// pass its own validation. `round_to` calls `pow`/`round`, which the
+    /// parser lowers directly to native `operator_*` blocks rather than a
+    /// `procedures_call`, so this also guards that lowering staying wired up.
+    #[test]
+    fn accepts_the_bundled_stdlib() {
+        let project = parse(crate::stdlib::SOURCE);
+        analyze(&project).unwrap();
+    }
+}
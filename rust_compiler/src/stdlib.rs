@@ -0,0 +1,19 @@
+//! Bundled SBText standard library.
+//!
+//! Mirrors the `append_stdlib`/`build_stdlib` pattern from the `sabre`
+//! backend: a small set of math helper custom blocks, compiled directly
+//! into the binary and merged in ahead of user imports unless `--no-std`
+//! is passed, so projects no longer need to hand-write them.
+
+/// The stdlib source, prepended to every project unless `--no-std` is set.
+pub const SOURCE: &str = include_str!("stdlib/prelude.sbtext");
+
+/// Top-level `define` names the stdlib provides, for the `list-std` subcommand.
+pub fn symbols() -> Vec<&'static str> {
+    SOURCE
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("define "))
+        .filter_map(|rest| rest.split('(').next())
+        .map(str::trim)
+        .collect()
+}
@@ -0,0 +1,122 @@
+//! SVG costume normalization for the native backend.
+//!
+//! Scratch costumes are authored at arbitrary sizes, but the VM expects a
+//! consistent 64x64 reference frame. This rewrites the root `<svg>` element's
+//! `width`/`height`/`viewBox` to 64x64 without touching the drawing commands,
+//! matching what the Python backend's normalization step produces.
+
+use anyhow::{bail, Result};
+
+const TARGET_SIZE: f64 = 64.0;
+
+/// Returns `svg` re-sized to a 64x64 viewport, preserving its contents.
+pub fn normalize_to_64x64(svg: &[u8]) -> Result<Vec<u8>> {
+    let text = std::str::from_utf8(svg)?;
+    let Some(tag_start) = text.find("<svg") else {
+        bail!("malformed SVG: missing <svg> root element");
+    };
+    let Some(tag_end) = text[tag_start..].find('>').map(|i| tag_start + i) else {
+        bail!("malformed SVG: unterminated <svg> root element");
+    };
+
+    let root = &text[tag_start..tag_end];
+    let rewritten_root = rewrite_root_attrs(root);
+
+    let mut out = String::with_capacity(text.len());
+    out.push_str(&text[..tag_start]);
+    out.push_str(&rewritten_root);
+    out.push_str(&text[tag_end..]);
+    Ok(out.into_bytes())
+}
+
+fn rewrite_root_attrs(root: &str) -> String {
+    // A self-closing root (`<svg .../>`) has its `/` trailing inside `root`
+    // (the caller split on the first `>`), so it must be carried past the
+    // newly-appended attributes rather than left stranded mid-tag.
+    let (root, self_closing) = match root.strip_suffix('/') {
+        Some(without_slash) => (without_slash, true),
+        None => (root, false),
+    };
+
+    let without_width = strip_attr(root, "width");
+    let without_height = strip_attr(&without_width, "height");
+    let without_view_box = strip_attr(&without_height, "viewBox");
+
+    format!(
+        "{} width=\"{size}\" height=\"{size}\" viewBox=\"0 0 {size} {size}\"{slash}",
+        without_view_box.trim_end(),
+        size = TARGET_SIZE,
+        slash = if self_closing { " /" } else { "" },
+    )
+}
+
+/// Removes `attr="..."` from `tag`, matching only at an attribute boundary
+/// (preceded by whitespace) so e.g. stripping `width` doesn't also eat the
+/// tail of `stroke-width="2"`.
+fn strip_attr(tag: &str, attr: &str) -> String {
+    let needle = format!("{attr}=\"");
+    let mut search_from = 0;
+    let start = loop {
+        let Some(offset) = tag[search_from..].find(&needle) else {
+            return tag.to_string();
+        };
+        let candidate = search_from + offset;
+        let at_boundary = tag[..candidate].chars().next_back().is_none_or(char::is_whitespace);
+        if at_boundary {
+            break candidate;
+        }
+        search_from = candidate + needle.len();
+    };
+    let value_start = start + needle.len();
+    let Some(len) = tag[value_start..].find('"') else {
+        return tag.to_string();
+    };
+    let end = value_start + len + 1;
+    format!("{}{}", &tag[..start], &tag[end..]).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_a_bare_svg() {
+        let out = normalize_to_64x64(br#"<svg width="10" height="10"></svg>"#).unwrap();
+        let out = std::str::from_utf8(&out).unwrap();
+        assert!(out.contains(r#"width="64""#));
+        assert!(out.contains(r#"viewBox="0 0 64 64""#));
+    }
+
+    /// Regression test: an XML prolog or leading comment puts a `>` before
+    /// `<svg`, which used to make `tag_start > tag_end` and panic on slicing.
+    #[test]
+    fn normalizes_an_svg_with_an_xml_prolog() {
+        let input = br#"<?xml version="1.0" encoding="UTF-8"?><svg width="10" height="10"></svg>"#;
+        let out = normalize_to_64x64(input).unwrap();
+        let out = std::str::from_utf8(&out).unwrap();
+        assert!(out.starts_with("<?xml"));
+        assert!(out.contains(r#"width="64""#));
+    }
+
+    /// Regression test: a bare substring match on `width="` used to also
+    /// match inside `stroke-width="..."`, tearing it into garbage and
+    /// leaving the original `width` attribute behind as a duplicate.
+    #[test]
+    fn does_not_clobber_an_attribute_whose_name_ends_with_the_target() {
+        let out = normalize_to_64x64(br#"<svg stroke-width="2" width="10" height="10"></svg>"#).unwrap();
+        let out = std::str::from_utf8(&out).unwrap();
+        assert!(out.contains(r#"stroke-width="2""#), "stroke-width must survive intact: {out}");
+        assert_eq!(out.matches(r#"width="64""#).count(), 1);
+        assert_eq!(out.matches("width=").count(), 2, "must not end up with a duplicate width attribute: {out}");
+    }
+
+    /// Regression test: a self-closing root's `/` used to be left stranded
+    /// mid-attribute-list instead of closing the rewritten tag.
+    #[test]
+    fn normalizes_a_self_closing_svg() {
+        let out = normalize_to_64x64(br#"<svg width="10" height="10"/>"#).unwrap();
+        let out = std::str::from_utf8(&out).unwrap();
+        assert!(out.ends_with("/>"), "expected a self-closing root: {out}");
+        assert!(out.contains(r#"width="64""#));
+    }
+}